@@ -0,0 +1,39 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+/// One entry from `ssap://com.webos.applicationManager/listApps`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct App {
+    pub id: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub icon: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct AppList {
+    apps: Vec<App>,
+}
+
+/// Response from `ssap://com.webos.applicationManager/getForegroundAppInfo`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForegroundApp {
+    pub app_id: String,
+    #[serde(default)]
+    pub process_id: String,
+    #[serde(default)]
+    pub window_id: String,
+}
+
+pub(crate) fn parse_app_list(payload: &Value) -> Result<Vec<App>, String> {
+    serde_json::from_value::<AppList>(payload.clone())
+        .map(|list| list.apps)
+        .map_err(|_| String::from("Could not parse app list"))
+}
+
+pub(crate) fn parse_foreground_app(payload: &Value) -> Result<ForegroundApp, String> {
+    serde_json::from_value(payload.clone())
+        .map_err(|_| String::from("Could not parse foreground app"))
+}
@@ -0,0 +1,111 @@
+use futures_util::{stream::SplitSink, SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    connect_async_tls_with_config, tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream,
+};
+
+use crate::build_connector;
+
+/// Named keys accepted by WebOS's pointer socket `type:button` frame.
+pub enum Button {
+    Up,
+    Down,
+    Left,
+    Right,
+    Back,
+    Home,
+    Enter,
+    Menu,
+    Dash,
+    Num0,
+    Num1,
+    Num2,
+    Num3,
+    Num4,
+    Num5,
+    Num6,
+    Num7,
+    Num8,
+    Num9,
+}
+
+impl Button {
+    fn name(&self) -> &'static str {
+        match self {
+            Button::Up => "UP",
+            Button::Down => "DOWN",
+            Button::Left => "LEFT",
+            Button::Right => "RIGHT",
+            Button::Back => "BACK",
+            Button::Home => "HOME",
+            Button::Enter => "ENTER",
+            Button::Menu => "MENU",
+            Button::Dash => "DASH",
+            Button::Num0 => "0",
+            Button::Num1 => "1",
+            Button::Num2 => "2",
+            Button::Num3 => "3",
+            Button::Num4 => "4",
+            Button::Num5 => "5",
+            Button::Num6 => "6",
+            Button::Num7 => "7",
+            Button::Num8 => "8",
+            Button::Num9 => "9",
+        }
+    }
+}
+
+/// Drives WebOS's secondary pointer/keyboard socket, negotiated via
+/// `WebosClient::get_pointer_input_client`. Unlike the main connection, this socket
+/// takes newline-delimited plaintext frames instead of JSON and has no responses to
+/// correlate, so it needs none of `WebosClient`'s id/pinky bookkeeping.
+pub struct PointerInputClient {
+    write: SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>,
+}
+
+impl PointerInputClient {
+    pub(crate) async fn connect(
+        socket_path: &str,
+        accept_invalid_certs: bool,
+    ) -> Result<PointerInputClient, String> {
+        let url = url::Url::parse(socket_path)
+            .map_err(|_| String::from("Could not parse pointer socket path"))?;
+        let connector = build_connector(accept_invalid_certs)?;
+        let (ws_stream, _) = connect_async_tls_with_config(url, None, false, connector)
+            .await
+            .map_err(|_| String::from("Failed to connect to pointer input socket"))?;
+        let (write, _read) = ws_stream.split();
+        Ok(PointerInputClient { write })
+    }
+
+    pub async fn button(&mut self, button: Button) -> Result<(), String> {
+        self.send_frame(&format!("type:button\nname:{}\n\n", button.name()))
+            .await
+    }
+
+    pub async fn move_cursor(&mut self, dx: i32, dy: i32, down: bool) -> Result<(), String> {
+        self.send_frame(&format!(
+            "type:move\ndx:{}\ndy:{}\ndown:{}\n\n",
+            dx,
+            dy,
+            down as u8
+        ))
+        .await
+    }
+
+    pub async fn click(&mut self) -> Result<(), String> {
+        self.send_frame("type:click\n\n").await
+    }
+
+    pub async fn scroll(&mut self, dx: i32, dy: i32) -> Result<(), String> {
+        self.send_frame(&format!("type:scroll\ndx:{}\ndy:{}\n\n", dx, dy))
+            .await
+    }
+
+    async fn send_frame(&mut self, frame: &str) -> Result<(), String> {
+        match self.write.send(Message::text(frame)).await {
+            Ok(_) => Ok(()),
+            Err(_) => Err(String::from("Could not send pointer input frame")),
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use futures_util::{
     future::ready,
     stream::{SplitSink, SplitStream},
-    SinkExt, StreamExt,
+    Stream, SinkExt, StreamExt,
 };
 use log::debug;
 use pinky_swear::{Pinky, PinkySwear};
@@ -11,11 +11,22 @@ use std::{
     collections::HashMap,
     sync::{Arc, Mutex},
 };
-use tokio::net::TcpStream;
+use tokio::{net::TcpStream, sync::mpsc};
+use tokio_stream::wrappers::UnboundedReceiverStream;
 use tokio_tungstenite::{
-    connect_async, tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream,
+    connect_async_tls_with_config, tungstenite::protocol::Message, Connector, MaybeTlsStream,
+    WebSocketStream,
 };
 
+mod input;
+pub use input::{Button, PointerInputClient};
+
+mod pairing;
+pub use pairing::{FileKeyStore, KeyStore, PairingState, WebosClientConfig};
+
+mod apps;
+pub use apps::{App, ForegroundApp};
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CommandRequest {
@@ -50,125 +61,93 @@ pub enum Command {
     Turn3DOn,
     Turn3DOff,
     GetServicesList,
+    GetPointerInputSocket,
+    ListApps,
+    LaunchApp { app_id: String, params: Option<Value> },
+    CloseApp(String),
+    GetForegroundApp,
 }
 pub struct CommandResponse {
     pub id: u8,
     pub payload: Option<Value>,
 }
 
-static HANDSHAKE: &'static str = r#"
-{
-    "type": "register",
-    "id": "register_0",
-    "payload": {
-        "forcePairing": false,
-        "pairingType": "PROMPT",
-        "client-key": "694552d52cbf3baca53ba60e7d71a067",
-        "manifest": {
-            "manifestVersion": 1,
-            "appVersion": "1.1",
-            "signed": {
-                "created": "20140509",
-                "appId": "com.lge.test",
-                "vendorId": "com.lge",
-                "localizedAppNames": {
-                    "": "LG Remote App",
-                    "ko-KR": "리모컨 앱",
-                    "zxx-XX": "ЛГ Rэмotэ AПП"
-                },
-                "localizedVendorNames": {
-                    "": "LG Electronics"
-                },
-                "permissions": [
-                    "TEST_SECURE",
-                    "CONTROL_INPUT_TEXT",
-                    "CONTROL_MOUSE_AND_KEYBOARD",
-                    "READ_INSTALLED_APPS",
-                    "READ_LGE_SDX",
-                    "READ_NOTIFICATIONS",
-                    "SEARCH",
-                    "WRITE_SETTINGS",
-                    "WRITE_NOTIFICATION_ALERT",
-                    "CONTROL_POWER",
-                    "READ_CURRENT_CHANNEL",
-                    "READ_RUNNING_APPS",
-                    "READ_UPDATE_INFO",
-                    "UPDATE_FROM_REMOTE_APP",
-                    "READ_LGE_TV_INPUT_EVENTS",
-                    "READ_TV_CURRENT_TIME"
-                ],
-                "serial": "2f930e2d2cfe083771f68e4fe7bb07"
-            },
-            "permissions": [
-                "LAUNCH",
-                "LAUNCH_WEBAPP",
-                "APP_TO_APP",
-                "CLOSE",
-                "TEST_OPEN",
-                "TEST_PROTECTED",
-                "CONTROL_AUDIO",
-                "CONTROL_DISPLAY",
-                "CONTROL_INPUT_JOYSTICK",
-                "CONTROL_INPUT_MEDIA_RECORDING",
-                "CONTROL_INPUT_MEDIA_PLAYBACK",
-                "CONTROL_INPUT_TV",
-                "CONTROL_POWER",
-                "READ_APP_STATUS",
-                "READ_CURRENT_CHANNEL",
-                "READ_INPUT_DEVICE_LIST",
-                "READ_NETWORK_STATE",
-                "READ_RUNNING_APPS",
-                "READ_TV_CHANNEL_LIST",
-                "WRITE_NOTIFICATION_TOAST",
-                "READ_POWER_STATE",
-                "READ_COUNTRY_INFO"
-            ],
-            "signatures": [
-                {
-                    "signatureVersion": 1,
-                    "signature": "eyJhbGdvcml0aG0iOiJSU0EtU0hBMjU2Iiwia2V5SWQiOiJ0ZXN0LXNpZ25pbmctY2VydCIsInNpZ25hdHVyZVZlcnNpb24iOjF9.hrVRgjCwXVvE2OOSpDZ58hR+59aFNwYDyjQgKk3auukd7pcegmE2CzPCa0bJ0ZsRAcKkCTJrWo5iDzNhMBWRyaMOv5zWSrthlf7G128qvIlpMT0YNY+n/FaOHE73uLrS/g7swl3/qH/BGFG2Hu4RlL48eb3lLKqTt2xKHdCs6Cd4RMfJPYnzgvI4BNrFUKsjkcu+WD4OO2A27Pq1n50cMchmcaXadJhGrOqH5YmHdOCj5NSHzJYrsW0HPlpuAx/ECMeIZYDh6RMqaFM2DXzdKX9NmmyqzJ3o/0lkk/N97gfVRLW5hA29yeAwaCViZNCP8iC9aO0q9fQojoa7NQnAtw=="
-                }
-            ]
-        }
-    }
-}
-"#;
-
 pub struct WebosClient {
     write: SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>,
-    registered: Arc<Mutex<bool>>,
+    pairing_state: Arc<Mutex<PairingState>>,
     next_command_id: Arc<Mutex<u8>>,
     pending_requests: Arc<Mutex<HashMap<u8, Pinky<CommandResponse>>>>,
+    pending_subscriptions: Arc<Mutex<HashMap<u8, mpsc::UnboundedSender<CommandResponse>>>>,
+    accept_invalid_certs: bool,
 }
 
 impl WebosClient {
+    /// Connects with a file-backed `KeyStore` at the default path, reusing a
+    /// previously paired client-key if one was saved there.
     pub async fn new(address: &str) -> Result<WebosClient, String> {
-        let url = url::Url::parse(address).expect("Could not parse given address");
-        let (ws_stream, _) = connect_async(url).await.expect("Failed to connect");
+        WebosClient::connect(address, WebosClientConfig::default()).await
+    }
+
+    /// Connects to the TV, presenting a stored client-key from `config.key_store` if
+    /// one is available. If the TV has never seen this app before, it shows an
+    /// on-TV PROMPT and `pairing_state()` reports `PromptShown` until the user
+    /// accepts; the client-key the TV then returns is handed to the `KeyStore` so
+    /// later connections can skip the prompt.
+    pub async fn connect(address: &str, config: WebosClientConfig) -> Result<WebosClient, String> {
+        let url = url::Url::parse(address)
+            .map_err(|_| String::from("Could not parse given address"))?;
+        let accept_invalid_certs = config.accept_invalid_certs;
+        let connector = build_connector(accept_invalid_certs)?;
+        let (ws_stream, _) = connect_async_tls_with_config(url, None, false, connector)
+            .await
+            .map_err(|_| String::from("Failed to connect"))?;
         debug!("WebSocket handshake has been successfully completed");
         let (mut write, read) = ws_stream.split();
 
-        let registered = Arc::from(Mutex::from(false));
+        let client_key = config.key_store.load();
+        let key_store: Arc<dyn KeyStore> = Arc::from(config.key_store);
+        let handshake = pairing::build_handshake(client_key.as_deref());
+
+        let pairing_state = Arc::from(Mutex::from(PairingState::PromptShown));
         let next_command_id = Arc::from(Mutex::from(0));
-        let reg = registered.clone();
+        let state = pairing_state.clone();
 
         let pending_requests = Arc::from(Mutex::from(HashMap::new()));
+        let pending_subscriptions = Arc::from(Mutex::from(HashMap::new()));
         let requests_to_process = pending_requests.clone();
-        tokio::spawn(
-            async move { process_messages_from_server(read, reg, requests_to_process).await },
-        );
-        write.send(Message::text(HANDSHAKE)).await.unwrap();
+        let subscriptions_to_process = pending_subscriptions.clone();
+        tokio::spawn(async move {
+            process_messages_from_server(
+                read,
+                state,
+                requests_to_process,
+                subscriptions_to_process,
+                key_store,
+            )
+            .await
+        });
+        write
+            .send(Message::text(handshake.to_string()))
+            .await
+            .map_err(|_| String::from("Could not send handshake"))?;
 
         Ok(WebosClient {
             write,
             next_command_id,
-            registered: registered.clone(),
+            pairing_state,
             pending_requests,
+            pending_subscriptions,
+            accept_invalid_certs,
         })
     }
 
+    /// Current state of the pairing handshake with the TV.
+    pub fn pairing_state(&self) -> PairingState {
+        *self.pairing_state.lock().unwrap()
+    }
+
     pub async fn send_command(&mut self, cmd: Command) -> Result<CommandResponse, String> {
-        if !*self.registered.lock().unwrap() {
+        if self.pairing_state() != PairingState::Registered {
             return Err(String::from("Not registered"));
         }
         match self.next_command_id.lock() {
@@ -192,27 +171,148 @@ impl WebosClient {
             Err(_) => Err(String::from("Could not generate next id")),
         }
     }
+
+    /// Negotiates WebOS's dedicated pointer/keyboard socket: requests a `socketPath`
+    /// over the main connection, then opens a second `WebSocketStream` to it so
+    /// navigation input can be sent without going through `send_command`'s id/response
+    /// bookkeeping.
+    pub async fn get_pointer_input_client(&mut self) -> Result<PointerInputClient, String> {
+        let response = self.send_command(Command::GetPointerInputSocket).await?;
+        let socket_path = response
+            .payload
+            .as_ref()
+            .and_then(|payload| payload["socketPath"].as_str())
+            .ok_or_else(|| String::from("Response did not contain a socketPath"))?;
+        PointerInputClient::connect(socket_path, self.accept_invalid_certs).await
+    }
+
+    /// Fetches the TV's installed application catalog as typed `App` entries instead
+    /// of the raw response payload.
+    pub async fn list_apps(&mut self) -> Result<Vec<App>, String> {
+        let response = self.send_command(Command::ListApps).await?;
+        apps::parse_app_list(response.payload.as_ref().unwrap_or(&Value::Null))
+    }
+
+    /// Launches `app_id`, optionally passing launch `params` through to the app.
+    pub async fn launch_app(
+        &mut self,
+        app_id: impl Into<String>,
+        params: Option<Value>,
+    ) -> Result<CommandResponse, String> {
+        self.send_command(Command::LaunchApp {
+            app_id: app_id.into(),
+            params,
+        })
+        .await
+    }
+
+    pub async fn close_app(&mut self, app_id: impl Into<String>) -> Result<CommandResponse, String> {
+        self.send_command(Command::CloseApp(app_id.into())).await
+    }
+
+    /// Fetches the currently foregrounded app as a typed `ForegroundApp`.
+    pub async fn get_foreground_app(&mut self) -> Result<ForegroundApp, String> {
+        let response = self.send_command(Command::GetForegroundApp).await?;
+        apps::parse_foreground_app(response.payload.as_ref().unwrap_or(&Value::Null))
+    }
+
+    /// Registers `cmd` as a WebOS `subscribe` request and returns the assigned id
+    /// alongside a stream that yields the TV's initial response plus every subsequent
+    /// push update for that id (the id is what `unsubscribe` expects). Unlike
+    /// `send_command`, the entry in `pending_subscriptions` is kept alive until
+    /// `unsubscribe` is called, or is reaped the next time a push fails to deliver
+    /// because the caller already dropped the stream.
+    pub async fn subscribe(
+        &mut self,
+        cmd: Command,
+    ) -> Result<(u8, impl Stream<Item = CommandResponse>), String> {
+        if self.pairing_state() != PairingState::Registered {
+            return Err(String::from("Not registered"));
+        }
+        let id = match self.next_command_id.lock() {
+            Ok(mut val) => {
+                *val += 1;
+                *val
+            }
+            Err(_) => return Err(String::from("Could not generate next id")),
+        };
+        let request = create_subscribe_command(id, cmd)
+            .ok_or_else(|| String::from("Could not build subscribe command"))?;
+        match self
+            .write
+            .send(Message::text(serde_json::to_string(&request).unwrap()))
+            .await
+        {
+            Ok(_) => {
+                let (tx, rx) = mpsc::unbounded_channel();
+                self.pending_subscriptions.lock().unwrap().insert(id, tx);
+                Ok((id, UnboundedReceiverStream::new(rx)))
+            }
+            Err(_) => Err(String::from("Could not send command")),
+        }
+    }
+
+    /// Tells the TV to stop pushing updates for `id` and drops the matching sender,
+    /// which closes the stream previously handed back by `subscribe`.
+    pub async fn unsubscribe(&mut self, id: u8) -> Result<(), String> {
+        self.pending_subscriptions.lock().unwrap().remove(&id);
+        match self
+            .write
+            .send(Message::text(
+                json!({ "type": "unsubscribe", "id": id }).to_string(),
+            ))
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(_) => Err(String::from("Could not send unsubscribe")),
+        }
+    }
 }
 
 async fn process_messages_from_server(
     sink: SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>,
-    registered: Arc<Mutex<bool>>,
+    pairing_state: Arc<Mutex<PairingState>>,
     pending_requests: Arc<Mutex<HashMap<u8, Pinky<CommandResponse>>>>,
+    pending_subscriptions: Arc<Mutex<HashMap<u8, mpsc::UnboundedSender<CommandResponse>>>>,
+    key_store: Arc<dyn KeyStore>,
 ) {
     sink.for_each(|message| match message {
         Ok(_message) => {
             if let Some(text_message) = _message.clone().into_text().ok() {
                 if let Ok(json) = serde_json::from_str::<Value>(&text_message) {
                     if json["type"] == "registered" {
-                        *registered.lock().unwrap() = true;
-                    } else if *registered.lock().unwrap() {
-                        let response = CommandResponse {
-                            id: json["id"].as_i64().unwrap() as u8,
-                            payload: Some(json["payload"].clone()),
-                        };
+                        if let Some(client_key) = json["payload"]["client-key"].as_str() {
+                            key_store.save(client_key);
+                        }
+                        *pairing_state.lock().unwrap() = PairingState::Registered;
+                    } else if json["type"] == "error"
+                        && *pairing_state.lock().unwrap() != PairingState::Registered
+                    {
+                        // Only a "register" response is a pairing rejection; once
+                        // registered, "error" is just a failed SSAP command and is
+                        // dispatched to its caller below like any other response.
+                        *pairing_state.lock().unwrap() = PairingState::Rejected;
+                    } else if *pairing_state.lock().unwrap() == PairingState::Registered {
+                        if let Some(id) = json["id"].as_i64() {
+                            let id = id as u8;
+                            let response = CommandResponse {
+                                id,
+                                payload: Some(json["payload"].clone()),
+                            };
 
-                        let requests = pending_requests.lock().unwrap();
-                        requests.get(&response.id).unwrap().swear(response);
+                            let mut subscriptions = pending_subscriptions.lock().unwrap();
+                            if let Some(sender) = subscriptions.get(&id) {
+                                if sender.send(response).is_err() {
+                                    subscriptions.remove(&id);
+                                }
+                            } else {
+                                drop(subscriptions);
+                                let mut requests = pending_requests.lock().unwrap();
+                                if let Some(pinky) = requests.remove(&id) {
+                                    pinky.swear(response);
+                                }
+                            }
+                        }
                     }
                 }
             }
@@ -223,6 +323,30 @@ async fn process_messages_from_server(
     .await
 }
 
+/// Builds the connector used for `wss://` sockets, given whether the TV's
+/// (usually self-signed) certificate should be accepted without validation. Shared by
+/// `WebosClient::connect` and `PointerInputClient::connect`, since the pointer socket
+/// is negotiated over the same `wss://` endpoint as the main connection.
+pub(crate) fn build_connector(accept_invalid_certs: bool) -> Result<Option<Connector>, String> {
+    if !accept_invalid_certs {
+        return Ok(None);
+    }
+    let connector = native_tls::TlsConnector::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .map_err(|_| String::from("Could not build TLS connector"))?;
+    Ok(Some(Connector::NativeTls(connector)))
+}
+
+/// Builds the same request payload as `create_command` but with `"type": "subscribe"`,
+/// so the TV keeps pushing updates on this id instead of closing it out after one response.
+fn create_subscribe_command(id: u8, cmd: Command) -> Option<CommandRequest> {
+    create_command(id, cmd).map(|request| CommandRequest {
+        r#type: String::from("subscribe"),
+        ..request
+    })
+}
+
 fn create_command(id: u8, cmd: Command) -> Option<CommandRequest> {
     match cmd {
         Command::CreateToast(text) => Some(CommandRequest {
@@ -369,5 +493,35 @@ fn create_command(id: u8, cmd: Command) -> Option<CommandRequest> {
             uri: String::from("ssap://com.webos.service.update/getCurrentSWInformation"),
             payload: None,
         }),
+        Command::GetPointerInputSocket => Some(CommandRequest {
+            id,
+            r#type: String::from("request"),
+            uri: String::from("ssap://com.webos.service.networkinput/getPointerInputSocket"),
+            payload: None,
+        }),
+        Command::ListApps => Some(CommandRequest {
+            id,
+            r#type: String::from("request"),
+            uri: String::from("ssap://com.webos.applicationManager/listApps"),
+            payload: None,
+        }),
+        Command::LaunchApp { app_id, params } => Some(CommandRequest {
+            id,
+            r#type: String::from("request"),
+            uri: String::from("ssap://com.webos.applicationManager/launch"),
+            payload: Some(json!({ "id": app_id, "params": params })),
+        }),
+        Command::CloseApp(app_id) => Some(CommandRequest {
+            id,
+            r#type: String::from("request"),
+            uri: String::from("ssap://com.webos.applicationManager/close"),
+            payload: Some(json!({ "id": app_id })),
+        }),
+        Command::GetForegroundApp => Some(CommandRequest {
+            id,
+            r#type: String::from("request"),
+            uri: String::from("ssap://com.webos.applicationManager/getForegroundAppInfo"),
+            payload: None,
+        }),
     }
 }